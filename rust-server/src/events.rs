@@ -0,0 +1,128 @@
+use futures_util::stream::Stream;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt as _;
+use tracing::{error, info, warn};
+
+use axum::response::sse::{Event, KeepAlive, Sse};
+
+/// Mirrors the JSON payload the `new_ledger` Postgres trigger emits.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LedgerEvent {
+    pub ledger_id: String,
+    pub task_id: String,
+    pub miner_id: i64,
+    pub performance: f64,
+}
+
+/// Installs the trigger (if missing) that calls `pg_notify('new_ledger', ...)`
+/// after every insert into `ledger`, so the notification is emitted inside
+/// the same transaction as the insert and no accepted row is ever missed.
+pub async fn install_ledger_trigger(db: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        CREATE OR REPLACE FUNCTION notify_new_ledger() RETURNS trigger AS $$
+        BEGIN
+            PERFORM pg_notify(
+                'new_ledger',
+                json_build_object(
+                    'ledger_id', NEW.id,
+                    'task_id', NEW.task_id,
+                    'miner_id', NEW.miner_id,
+                    'performance', NEW.performance
+                )::text
+            );
+            RETURN NEW;
+        END;
+        $$ LANGUAGE plpgsql;
+        "#,
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query(
+        r#"
+        DROP TRIGGER IF EXISTS ledger_notify_trigger ON ledger;
+        CREATE TRIGGER ledger_notify_trigger
+            AFTER INSERT ON ledger
+            FOR EACH ROW EXECUTE FUNCTION notify_new_ledger();
+        "#,
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Spawns a background task holding a dedicated `LISTEN new_ledger`
+/// connection and fans parsed notifications out over `tx`. Runs for the
+/// lifetime of the process; reconnects on listener error so a dropped
+/// connection doesn't silently stop the feed.
+pub fn spawn_ledger_listener(database_url: String, tx: broadcast::Sender<LedgerEvent>) {
+    tokio::spawn(async move {
+        loop {
+            match PgListener::connect(&database_url).await {
+                Ok(mut listener) => {
+                    if let Err(e) = listener.listen("new_ledger").await {
+                        error!("failed to LISTEN new_ledger: {}", e);
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                        continue;
+                    }
+                    info!("listening for new_ledger notifications");
+                    loop {
+                        match listener.recv().await {
+                            Ok(notification) => {
+                                match serde_json::from_str::<LedgerEvent>(notification.payload()) {
+                                    Ok(event) => {
+                                        let _ = tx.send(event);
+                                    }
+                                    Err(e) => warn!("bad new_ledger payload: {}", e),
+                                }
+                            }
+                            Err(e) => {
+                                error!("new_ledger listener error: {}", e);
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("failed to open listener connection: {}", e);
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    });
+}
+
+/// `GET /events[?task_id=...]` — streams accepted submissions as they
+/// land, optionally filtered to a single task.
+pub async fn events(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<crate::AppState>>,
+    axum::extract::Query(params): axum::extract::Query<EventsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let rx = state.ledger_events.subscribe();
+    let task_filter = params.task_id;
+    let stream = BroadcastStream::new(rx).filter_map(move |msg| match msg {
+        Ok(event) => {
+            if task_filter.as_deref().is_some_and(|t| t != event.task_id) {
+                return None;
+            }
+            match serde_json::to_string(&event) {
+                Ok(json) => Some(Ok(Event::default().data(json))),
+                Err(_) => None,
+            }
+        }
+        Err(_) => None,
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    pub task_id: Option<String>,
+}
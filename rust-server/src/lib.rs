@@ -0,0 +1,384 @@
+use axum::{
+    extract::{Multipart, State},
+    routing::{get, post},
+    Json, Router, response::IntoResponse
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use ed25519_dalek::{PublicKey, Signature, Verifier};
+use sha2::{Sha256, Digest};
+use uuid::Uuid;
+use std::{sync::Arc, time::{Duration, SystemTime, UNIX_EPOCH}};
+use tracing::{info, error};
+use bytes::Bytes;
+
+mod events;
+mod merkle;
+mod nonce_store;
+mod rate_limit;
+mod store;
+mod tasks;
+mod validation_samples;
+mod validators;
+pub use events::LedgerEvent;
+pub use nonce_store::{MockNonceStore, NonceStore, RedisNonceStore};
+use rate_limit::check_rate_limit;
+pub use rate_limit::{LocalRateLimiter, RateLimitConfig};
+pub use store::{ArtifactStore, FsArtifactStore};
+pub use validators::ValidatorConfig;
+use tokio::sync::broadcast;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub db: PgPool,
+    pub local_limiter: Arc<LocalRateLimiter>,
+    pub ledger_events: broadcast::Sender<LedgerEvent>,
+    pub artifact_store: Arc<dyn ArtifactStore>,
+    pub nonce_store: Arc<dyn NonceStore>,
+    pub validator_config: ValidatorConfig,
+    // map miner_id -> public_key hex can be loaded from DB into cache in prod
+}
+
+#[derive(Deserialize)]
+pub struct SubmissionPayload {
+    pub task_id: String,
+    pub miner_id: i64,
+    pub performance: f32,
+    pub artifact_hash: String,
+    pub hyperparameters: serde_json::Value,
+    pub timestamp: u64,
+    pub nonce: u64,
+    /// Predictions + Merkle inclusion proofs for the server-chosen spot-check
+    /// indices. The input/label preimages are *not* supplied here — the
+    /// server looks those up itself, so a miner can't "pass" by echoing its
+    /// claimed label back as the prediction.
+    pub spot_check: Vec<SpotCheckEntry>,
+}
+
+#[derive(Deserialize)]
+pub struct SpotCheckEntry {
+    pub index: u64,
+    pub prediction_hex: String,
+    /// Sibling hashes, leaf-to-root order, each hex-encoded.
+    pub proof_hex: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ApiResponse {
+    pub status: String,
+    pub reason: Option<String>
+}
+
+/// `submit`'s response: like [`ApiResponse`], plus the ledger id of a
+/// proposal so the caller can poll `GET /attestations/:ledger_id` while
+/// it awaits validator quorum.
+#[derive(Serialize, Deserialize)]
+pub struct SubmitResponse {
+    pub status: String,
+    pub reason: Option<String>,
+    pub ledger_id: Option<String>,
+}
+
+async fn submit(
+    State(state): State<Arc<AppState>>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    // Expect fields:
+    // - payload (json)
+    // - signature (hex)
+    // - artifact (file)
+    let mut payload_json: Option<String> = None;
+    let mut signature_hex: Option<String> = None;
+    let mut artifact_bytes: Option<Bytes> = None;
+
+    while let Some(field) = multipart.next_field().await.unwrap() {
+        let name = field.name().map(|s| s.to_string()).unwrap_or_default();
+        match name.as_str() {
+            "payload" => {
+                payload_json = Some(field.text().await.unwrap());
+            }
+            "signature" => {
+                signature_hex = Some(field.text().await.unwrap());
+            }
+            "artifact" => {
+                artifact_bytes = Some(field.bytes().await.unwrap());
+            }
+            _ => {}
+        }
+    }
+
+    if payload_json.is_none() || signature_hex.is_none() || artifact_bytes.is_none() {
+        return Json(SubmitResponse {status: "rejected".into(), reason: Some("missing fields".into()), ledger_id: None});
+    }
+
+    let payload_json = payload_json.unwrap();
+    let signature_hex = signature_hex.unwrap();
+    let artifact_bytes = artifact_bytes.unwrap();
+
+    // Parse payload
+    let payload: SubmissionPayload = match serde_json::from_str(&payload_json) {
+        Ok(p) => p,
+        Err(e) => {
+            error!("bad payload json: {}", e);
+            return Json(SubmitResponse {status: "rejected".into(), reason: Some("invalid payload json".into()), ledger_id: None});
+        }
+    };
+
+    // 1) The miner must currently hold the lease on this task to submit for it
+    let task = match tasks::leased_task(&state.db, &payload.task_id, payload.miner_id).await {
+        Ok(Some(t)) => t,
+        Ok(None) => return Json(SubmitResponse {status: "rejected".into(), reason: Some("no_active_lease".into()), ledger_id: None}),
+        Err(e) => {
+            error!("leased_task lookup error: {}", e);
+            return Json(SubmitResponse {status: "rejected".into(), reason: Some("db error".into()), ledger_id: None});
+        }
+    };
+
+    // 2) Rate-limit the miner for this task before touching Redis for anything else
+    match check_rate_limit(&state.local_limiter, payload.miner_id, &task.rate_limit).await {
+        Ok(true) => {}
+        Ok(false) => return Json(SubmitResponse {status: "rejected".into(), reason: Some("rate_limited".into()), ledger_id: None}),
+        Err(e) => {
+            error!("rate limit check error: {}", e);
+            return Json(SubmitResponse {status: "rejected".into(), reason: Some("redis error".into()), ledger_id: None});
+        }
+    }
+
+    // 3) Verify nonce/replay, keyed on both the signature and the client nonce
+    let sig_key = format!("nonce:{}:{}", signature_hex, payload.nonce);
+    match state.nonce_store.claim(&sig_key, 300).await {
+        Ok(true) => {}
+        Ok(false) => return Json(SubmitResponse {status: "rejected".into(), reason: Some("replay".into()), ledger_id: None}),
+        Err(e) => {
+            error!("nonce store error: {}", e);
+            return Json(SubmitResponse {status: "rejected".into(), reason: Some("redis error".into()), ledger_id: None});
+        }
+    }
+
+    // 4) Verify timestamp freshness
+    if let Err(reason) = check_timestamp_freshness(payload.timestamp) {
+        return Json(SubmitResponse {status: "rejected".into(), reason: Some(reason.into()), ledger_id: None});
+    }
+
+    // 5) Verify artifact hash matches
+    let mut hasher = Sha256::new();
+    hasher.update(&artifact_bytes);
+    let computed = hex::encode(hasher.finalize());
+    if computed != payload.artifact_hash {
+        return Json(SubmitResponse {status: "rejected".into(), reason: Some("artifact hash mismatch".into()), ledger_id: None});
+    }
+
+    // 6) Verify signature using stored miner public key
+    let pubkey_hex: String = match sqlx::query_as("SELECT public_key FROM miners WHERE miner_id = $1")
+        .bind(payload.miner_id)
+        .fetch_one(&state.db)
+        .await
+    {
+        Ok((pk,)) => pk,
+        Err(e) => {
+            error!("miner pk lookup error: {}", e);
+            return Json(SubmitResponse {
+                status: "rejected".into(),
+                reason: Some("unknown miner".into()),
+                ledger_id: None,
+            });
+        }
+    };
+    let pubkey_bytes: Vec<u8> = match hex::decode(pubkey_hex) {
+        Ok(b) => b,
+        Err(_) => return Json(SubmitResponse {status: "rejected".into(), reason: Some("invalid pubkey".into()), ledger_id: None}),
+    };
+    let vk = match PublicKey::from_bytes(&pubkey_bytes) {
+        Ok(k) => k,
+        Err(_) => return Json(SubmitResponse {status: "rejected".into(), reason: Some("bad pubkey".into()), ledger_id: None}),
+    };
+    let sig_bytes: Vec<u8> = match hex::decode(&signature_hex) {
+        Ok(b) => b,
+        Err(_) => return Json(SubmitResponse {status: "rejected".into(), reason: Some("bad signature".into()), ledger_id: None}),
+    };
+    let sig = match Signature::from_bytes(&sig_bytes) {
+        Ok(s) => s,
+        Err(_) => return Json(SubmitResponse {status: "rejected".into(), reason: Some("signature parse error".into()), ledger_id: None}),
+    };
+    if vk.verify(payload_json.as_bytes(), &sig).is_err() {
+        return Json(SubmitResponse {status: "rejected".into(), reason: Some("bad_signature".into()), ledger_id: None});
+    }
+
+    // 7) Verify the Merkle spot-check proofs and the claimed performance.
+    // Shared with `validators::attest`, so a validator re-runs exactly the
+    // same check rather than trusting the proposer's say-so.
+    if let Err(reason) = verify_spot_check(&state.db, &task, &payload).await {
+        return Json(SubmitResponse {status: "rejected".into(), reason: Some(reason.into()), ledger_id: None});
+    }
+
+    // 8) Store the artifact content-addressed, deduplicating identical uploads
+    let already_stored = state.artifact_store.exists(&payload.artifact_hash).await;
+    if !already_stored {
+        if let Err(e) = state.artifact_store.put(&payload.artifact_hash, &artifact_bytes).await {
+            error!("artifact store put error: {}", e);
+            return Json(SubmitResponse {status: "rejected".into(), reason: Some("storage error".into()), ledger_id: None});
+        }
+    }
+    let artifact_location = match state.artifact_store.location_for(&payload.artifact_hash) {
+        Ok(loc) => loc,
+        Err(_) => return Json(SubmitResponse {status: "rejected".into(), reason: Some("invalid artifact hash".into()), ledger_id: None}),
+    };
+
+    // 9) Propose the submission for finalization. With no validator quorum
+    // configured this finalizes immediately (single-node mode); otherwise
+    // it's broadcast to peers in the background and finalized once M of
+    // them attest, or expired if quorum is never reached.
+    let ledger_id = Uuid::new_v4().to_string();
+    let proposal = validators::ProposedLedgerRow {
+        ledger_id: ledger_id.clone(),
+        task_id: payload.task_id.clone(),
+        miner_id: payload.miner_id,
+        performance: payload.performance,
+        hyperparameters: payload.hyperparameters,
+        artifact_hash: payload.artifact_hash,
+        artifact_location,
+        timestamp: payload.timestamp,
+    };
+    let digest = validators::submission_digest(&proposal);
+    if let Err(e) = validators::propose(&state.db, &proposal, &digest).await {
+        error!("pending_ledger insert error: {}", e);
+        return Json(SubmitResponse {status: "rejected".into(), reason: Some("db error".into()), ledger_id: None});
+    }
+
+    // Move the lease to `proposed` now, not just at finalize-time, so a
+    // concurrent resubmission for the same task_id fails the lease check in
+    // step 1 instead of racing this proposal to a second, independently
+    // quorate pending_ledger row.
+    match tasks::mark_proposed(&state.db, &task.task_id, payload.miner_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            error!("task {} no longer leased by {} at propose-time", task.task_id, payload.miner_id);
+            return Json(SubmitResponse {status: "rejected".into(), reason: Some("no_active_lease".into()), ledger_id: None});
+        }
+        Err(e) => {
+            error!("mark_proposed error: {}", e);
+            return Json(SubmitResponse {status: "rejected".into(), reason: Some("db error".into()), ledger_id: None});
+        }
+    }
+
+    if !state.validator_config.consensus_enabled() {
+        if let Err(e) = validators::finalize(&state.db, &ledger_id, &task.task_id).await {
+            error!("finalize error: {}", e);
+            return Json(SubmitResponse {status: "rejected".into(), reason: Some("db error".into()), ledger_id: None});
+        }
+        info!("accepted submission {}", ledger_id);
+        return Json(SubmitResponse {status: "accepted".into(), reason: None, ledger_id: Some(ledger_id)});
+    }
+
+    validators::spawn_attestation_round(
+        state.db.clone(),
+        state.validator_config.clone(),
+        ledger_id.clone(),
+        task.task_id.clone(),
+        digest,
+        payload_json,
+        signature_hex,
+    );
+    info!("proposed submission {} awaiting validator quorum", ledger_id);
+    Json(SubmitResponse {status: "proposed".into(), reason: None, ledger_id: Some(ledger_id)})
+}
+
+/// Rejects timestamps more than 60s in the future (clock skew) or more
+/// than 300s in the past (staleness). Shared by `submit` and
+/// `validators::attest` so a validator enforces the same freshness window
+/// the proposer did.
+pub(crate) fn check_timestamp_freshness(timestamp: u64) -> Result<(), &'static str> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    if timestamp > now + 60 || now.saturating_sub(timestamp) > 300 {
+        return Err("stale timestamp");
+    }
+    Ok(())
+}
+
+/// Recomputes each spot-check leaf from the server's own withheld
+/// `validation_samples` (never the payload), verifies its Merkle inclusion
+/// proof against `task`'s root, and checks the sampled accuracy against
+/// `payload.performance` within `task.performance_tolerance`. Shared by
+/// `submit` and `validators::attest`, so a validator re-derives the same
+/// verdict instead of trusting the proposer's say-so.
+pub(crate) async fn verify_spot_check(
+    db: &PgPool,
+    task: &tasks::TaskInfo,
+    payload: &SubmissionPayload,
+) -> Result<(), &'static str> {
+    let expected_indices = merkle::expected_indices(&payload.artifact_hash, task.validation_set_size, task.spot_check_k);
+    let supplied_indices: Vec<u64> = payload.spot_check.iter().map(|e| e.index).collect();
+    if supplied_indices != expected_indices {
+        return Err("invalid_merkle_proof");
+    }
+    let root: [u8; 32] = match hex::decode(&task.validation_data_hash).ok().and_then(|b| b.try_into().ok()) {
+        Some(r) => r,
+        None => {
+            error!("task {} has a malformed validation_data_hash", task.task_id);
+            return Err("invalid_merkle_proof");
+        }
+    };
+    let samples = validation_samples::fetch_samples(db, &task.task_id, &expected_indices)
+        .await
+        .map_err(|e| {
+            error!("validation_samples lookup error: {}", e);
+            "db error"
+        })?;
+    let mut spot_check_leaves = Vec::with_capacity(payload.spot_check.len());
+    for entry in &payload.spot_check {
+        let (input, label) = samples.get(&entry.index).cloned().ok_or("invalid_merkle_proof")?;
+        let prediction = hex::decode(&entry.prediction_hex).map_err(|_| "invalid_merkle_proof")?;
+        let proof: Option<Vec<[u8; 32]>> = entry
+            .proof_hex
+            .iter()
+            .map(|s| hex::decode(s).ok().and_then(|b| b.try_into().ok()))
+            .collect();
+        let proof = proof.ok_or("invalid_merkle_proof")?;
+        let leaf = merkle::leaf_hash(entry.index, &input, &label);
+        if !merkle::verify_proof(leaf, entry.index, &proof, &root) {
+            return Err("invalid_merkle_proof");
+        }
+        spot_check_leaves.push(merkle::SpotCheckLeaf { label, prediction });
+    }
+    let sampled_accuracy = merkle::empirical_accuracy(&spot_check_leaves);
+    if payload.performance - sampled_accuracy > task.performance_tolerance {
+        return Err("performance_overclaim");
+    }
+    Ok(())
+}
+
+/// Builds the full app state from environment variables, for the real
+/// `main` entrypoint. Tests construct `AppState` by hand instead, so they
+/// can swap in `MockNonceStore` and a temp-dir `FsArtifactStore`.
+pub async fn init_state_from_env() -> anyhow::Result<Arc<AppState>> {
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL required");
+    let redis_url = std::env::var("REDIS_URL").unwrap_or("redis://127.0.0.1/".to_string());
+    let db = PgPool::connect(&database_url).await?;
+    sqlx::migrate!("./migrations").run(&db).await?;
+
+    events::install_ledger_trigger(&db).await?;
+    let (ledger_events, _) = broadcast::channel(1024);
+    events::spawn_ledger_listener(database_url, ledger_events.clone());
+    tasks::spawn_lease_sweeper(db.clone(), Duration::from_secs(30));
+    validators::spawn_proposal_sweeper(db.clone(), Duration::from_secs(30));
+
+    let local_limiter = Arc::new(LocalRateLimiter::new(redis_url.clone(), 50, Duration::from_millis(500)));
+    let artifact_root = std::env::var("ARTIFACT_STORE_PATH").unwrap_or("./artifacts".to_string());
+    let artifact_store: Arc<dyn ArtifactStore> = Arc::new(store::FsArtifactStore::new(artifact_root));
+    let nonce_store: Arc<dyn NonceStore> = Arc::new(RedisNonceStore::connect(&redis_url).await?);
+    let validator_config = ValidatorConfig::from_env();
+
+    Ok(Arc::new(AppState { db, local_limiter, ledger_events, artifact_store, nonce_store, validator_config }))
+}
+
+pub fn build_router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/get_task", post(tasks::get_task))
+        .route("/heartbeat", post(tasks::heartbeat))
+        .route("/submit", post(submit))
+        .route("/events", get(events::events))
+        .route("/artifact/:hash", get(store::get_artifact))
+        .route("/attest", post(validators::attest))
+        .route("/attestations/:ledger_id", get(validators::attestations))
+        .with_state(state)
+}
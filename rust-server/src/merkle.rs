@@ -0,0 +1,67 @@
+use rand::seq::index::sample;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use sha2::{Digest, Sha256};
+
+/// One already-verified spot-check entry: the server's withheld label (from
+/// `validation_samples`, not the miner) and the model's claimed prediction
+/// for it, kept around only long enough for [`empirical_accuracy`] to
+/// compare them.
+pub struct SpotCheckLeaf {
+    pub label: Vec<u8>,
+    pub prediction: Vec<u8>,
+}
+
+/// `H(index || input || label)`, matching the leaf construction used when
+/// the task's Merkle root was published.
+pub fn leaf_hash(index: u64, input: &[u8], label: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(index.to_le_bytes());
+    hasher.update(input);
+    hasher.update(label);
+    hasher.finalize().into()
+}
+
+/// Recomputes the root from `leaf` by folding in `proof` sibling-by-sibling,
+/// using `index`'s bits to decide left/right at each level.
+pub fn verify_proof(leaf: [u8; 32], index: u64, proof: &[[u8; 32]], root: &[u8; 32]) -> bool {
+    let mut current = leaf;
+    for (depth, sibling) in proof.iter().enumerate() {
+        let mut hasher = Sha256::new();
+        if (index >> depth) & 1 == 0 {
+            hasher.update(current);
+            hasher.update(sibling);
+        } else {
+            hasher.update(sibling);
+            hasher.update(current);
+        }
+        current = hasher.finalize().into();
+    }
+    &current == root
+}
+
+/// Deterministically derives the `k` validation-set indices the miner must
+/// supply proofs for, seeded from the artifact hash so a miner can't choose
+/// easy samples after seeing the task's validation set.
+pub fn expected_indices(artifact_hash: &str, validation_set_size: u64, k: u64) -> Vec<u64> {
+    let digest = Sha256::digest(artifact_hash.as_bytes());
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&digest);
+    let mut rng = ChaCha8Rng::from_seed(seed);
+    let k = k.min(validation_set_size) as usize;
+    let mut indices: Vec<u64> = sample(&mut rng, validation_set_size as usize, k)
+        .into_iter()
+        .map(|i| i as u64)
+        .collect();
+    indices.sort_unstable();
+    indices
+}
+
+/// Fraction of supplied predictions that match their supplied labels.
+pub fn empirical_accuracy(leaves: &[SpotCheckLeaf]) -> f32 {
+    if leaves.is_empty() {
+        return 0.0;
+    }
+    let correct = leaves.iter().filter(|l| l.prediction == l.label).count();
+    correct as f32 / leaves.len() as f32
+}
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Anti-replay storage: claims a key exactly once within its TTL. Returning
+/// `false` means the key was already claimed (a replay); returning `true`
+/// means this call is the one that claimed it.
+#[async_trait]
+pub trait NonceStore: Send + Sync {
+    async fn claim(&self, key: &str, ttl_secs: u64) -> anyhow::Result<bool>;
+}
+
+/// Real implementation backed by Redis `SETNX`. Holds a `ConnectionManager`,
+/// which multiplexes over a single auto-reconnecting connection instead of
+/// opening a fresh one per request.
+pub struct RedisNonceStore {
+    conn: redis::aio::ConnectionManager,
+}
+
+impl RedisNonceStore {
+    pub async fn connect(redis_url: &str) -> anyhow::Result<Self> {
+        let client = redis::Client::open(redis_url)?;
+        let conn = client.get_connection_manager().await?;
+        Ok(RedisNonceStore { conn })
+    }
+}
+
+#[async_trait]
+impl NonceStore for RedisNonceStore {
+    async fn claim(&self, key: &str, ttl_secs: u64) -> anyhow::Result<bool> {
+        let mut conn = self.conn.clone();
+        let claimed: bool = conn.set_nx(key, 1).await?;
+        if claimed {
+            let _: () = conn.expire(key, ttl_secs as i64).await.unwrap_or(());
+        }
+        Ok(claimed)
+    }
+}
+
+/// In-memory mock for unit/integration tests, so `submit` can be exercised
+/// end-to-end without a live Redis instance.
+#[derive(Default)]
+pub struct MockNonceStore {
+    claimed: Mutex<HashMap<String, Instant>>,
+}
+
+impl MockNonceStore {
+    pub fn new() -> Self {
+        MockNonceStore::default()
+    }
+}
+
+#[async_trait]
+impl NonceStore for MockNonceStore {
+    async fn claim(&self, key: &str, ttl_secs: u64) -> anyhow::Result<bool> {
+        let mut claimed = self.claimed.lock().unwrap();
+        let now = Instant::now();
+        if let Some(expires_at) = claimed.get(key) {
+            if *expires_at > now {
+                return Ok(false);
+            }
+        }
+        claimed.insert(key.to_string(), now + Duration::from_secs(ttl_secs));
+        Ok(true)
+    }
+}
@@ -0,0 +1,188 @@
+use dashmap::DashMap;
+use redis::AsyncCommands;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::error;
+
+/// Per-task rate limit knobs, published alongside a `TaskInfo`.
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct RateLimitConfig {
+    pub window_secs: u64,
+    pub max_requests: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig { window_secs: 60, max_requests: 30 }
+    }
+}
+
+struct LocalCounter {
+    bucket: AtomicU64,
+    /// Total requests seen locally for `bucket`, used for the cheap
+    /// obviously-over-limit rejection. Never reset by a flush, only by a
+    /// bucket rollover.
+    count: AtomicU64,
+    /// Requests seen locally since the last flush, not yet reconciled into
+    /// Redis. Reconciled via `INCRBY` and reset to 0 on every flush, so no
+    /// request is ever double-counted or dropped.
+    pending: AtomicU64,
+    last_flush: std::sync::Mutex<Instant>,
+}
+
+/// Deferred local rate limiter: obviously-over-limit miners are rejected
+/// from an in-process `DashMap` without a Redis round-trip. Every request
+/// still increments a per-miner pending delta, which is reconciled into
+/// the real sliding-window counters in Redis via `INCRBY` once that miner
+/// has accumulated `flush_every` pending requests or `flush_interval` has
+/// elapsed since their last flush, so the in-process view never drifts far
+/// from the shared one and no request goes unrecorded.
+pub struct LocalRateLimiter {
+    redis_url: String,
+    counts: DashMap<i64, LocalCounter>,
+    flush_every: u64,
+    flush_interval: Duration,
+}
+
+impl LocalRateLimiter {
+    pub fn new(redis_url: impl Into<String>, flush_every: u64, flush_interval: Duration) -> Self {
+        LocalRateLimiter {
+            redis_url: redis_url.into(),
+            counts: DashMap::new(),
+            flush_every,
+            flush_interval,
+        }
+    }
+
+    /// Bumps `miner_id`'s local counters for `bucket` and reports whether
+    /// it's already well past `limit` purely from the in-process view (no
+    /// I/O), plus whether this request pushed it over its own flush
+    /// threshold. If `bucket` rolled over since the last call, the
+    /// previous bucket's still-unreconciled delta is carried out
+    /// separately as `(stale_bucket, stale_delta)` rather than folded into
+    /// the new bucket's count, so it can be flushed against the bucket it
+    /// actually belongs to.
+    fn bump(&self, miner_id: i64, bucket: i64, limit: u64) -> (bool, bool, Option<(i64, u64)>) {
+        let entry = self.counts.entry(miner_id).or_insert_with(|| LocalCounter {
+            bucket: AtomicU64::new(bucket as u64),
+            count: AtomicU64::new(0),
+            pending: AtomicU64::new(0),
+            last_flush: std::sync::Mutex::new(Instant::now()),
+        });
+        let prev_bucket = entry.bucket.swap(bucket as u64, Ordering::SeqCst) as i64;
+        let stale = if prev_bucket != bucket {
+            entry.count.store(0, Ordering::SeqCst);
+            match entry.pending.swap(0, Ordering::SeqCst) {
+                0 => None,
+                leftover => Some((prev_bucket, leftover)),
+            }
+        } else {
+            None
+        };
+        let count = entry.count.fetch_add(1, Ordering::SeqCst) + 1;
+        let pending = entry.pending.fetch_add(1, Ordering::SeqCst) + 1;
+        let due = pending >= self.flush_every
+            || entry
+                .last_flush
+                .lock()
+                .map(|t| t.elapsed() >= self.flush_interval)
+                .unwrap_or(false);
+        (count > limit, due, stale)
+    }
+
+    /// Takes `miner_id`'s accumulated pending delta for reconciliation and
+    /// resets it, returning the delta to `INCRBY` into Redis.
+    fn take_pending(&self, miner_id: i64) -> u64 {
+        match self.counts.get(&miner_id) {
+            Some(entry) => {
+                if let Ok(mut t) = entry.last_flush.lock() {
+                    *t = Instant::now();
+                }
+                entry.pending.swap(0, Ordering::SeqCst)
+            }
+            None => 0,
+        }
+    }
+}
+
+/// Checks (and records) a request against the sliding-window limit for
+/// `miner_id`, consulting the local limiter first and only falling
+/// through to Redis when the request isn't already obviously over the
+/// limit and this miner's own flush is due.
+pub async fn check_rate_limit(
+    local: &LocalRateLimiter,
+    miner_id: i64,
+    config: &RateLimitConfig,
+) -> Result<bool, redis::RedisError> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let window_secs = config.window_secs.max(1);
+    let bucket = (now / window_secs) as i64;
+
+    let (over_limit, flush_due, stale) = local.bump(miner_id, bucket, config.max_requests);
+    if let Some((stale_bucket, stale_delta)) = stale {
+        // Reconciles the bucket this miner rolled out of, so its leftover
+        // pending delta lands on the bucket it was actually accrued in
+        // instead of being folded into the new one.
+        if let Err(e) =
+            sliding_window_check(&local.redis_url, miner_id, stale_bucket, stale_delta, config).await
+        {
+            error!("stale-bucket rate limit flush error: {}", e);
+        }
+    }
+    if over_limit {
+        return Ok(false);
+    }
+
+    if !flush_due {
+        return Ok(true);
+    }
+
+    // A concurrent caller's flush may have already reconciled this miner's
+    // pending delta down to 0 -- still run the authoritative check (just
+    // with nothing to add) rather than skip it, so a request that lands
+    // right on a flush boundary is judged against the real weighted count
+    // instead of only the cheap local one above.
+    let delta = local.take_pending(miner_id);
+    sliding_window_check(&local.redis_url, miner_id, bucket, delta, config).await
+}
+
+/// The authoritative check: `INCRBY`s `rl:{miner_id}:{bucket}` by `delta`
+/// (the miner's accumulated-but-not-yet-reconciled local count), sets its
+/// expiry to 2x the window on first increment, and weighs the previous
+/// bucket's count by the elapsed fraction of the current window so the
+/// limit doesn't reset hard at bucket boundaries.
+async fn sliding_window_check(
+    redis_url: &str,
+    miner_id: i64,
+    bucket: i64,
+    delta: u64,
+    config: &RateLimitConfig,
+) -> Result<bool, redis::RedisError> {
+    let client = redis::Client::open(redis_url)?;
+    let mut con = client.get_async_connection().await?;
+
+    let window_secs = config.window_secs.max(1);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let elapsed_in_window = now % window_secs;
+
+    let cur_key = format!("rl:{}:{}", miner_id, bucket);
+    let prev_key = format!("rl:{}:{}", miner_id, bucket - 1);
+
+    let cur_count: u64 = con.incr(&cur_key, delta).await?;
+    if cur_count == delta {
+        let _: () = con
+            .expire(&cur_key, (window_secs * 2) as i64)
+            .await
+            .unwrap_or(());
+    }
+    let prev_count: u64 = con.get(&prev_key).await.unwrap_or(0);
+
+    let weight = 1.0 - (elapsed_in_window as f64 / window_secs as f64);
+    let weighted = cur_count as f64 + prev_count as f64 * weight;
+
+    if weighted > config.max_requests as f64 {
+        error!("miner {} exceeded rate limit ({:.1}/{})", miner_id, weighted, config.max_requests);
+        return Ok(false);
+    }
+    Ok(true)
+}
@@ -0,0 +1,102 @@
+use async_trait::async_trait;
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::io::AsyncRead;
+use tokio_util::io::ReaderStream;
+
+/// Backend-agnostic content-addressable storage for submitted artifacts.
+/// Implementations are keyed by the artifact's hex SHA-256, so `put` is
+/// naturally idempotent and `exists` lets callers skip re-uploading.
+#[async_trait]
+pub trait ArtifactStore: Send + Sync {
+    async fn put(&self, hash: &str, bytes: &[u8]) -> anyhow::Result<()>;
+    async fn get(&self, hash: &str) -> anyhow::Result<Box<dyn AsyncRead + Send + Unpin>>;
+    async fn exists(&self, hash: &str) -> bool;
+
+    /// The location to record in the ledger for an artifact stored under
+    /// `hash`, e.g. `fs:ab/cd/<hash>` or an S3 URI. Lets `submit` record
+    /// where the configured backend actually put the artifact instead of
+    /// assuming any one implementation's layout. Errors on a malformed
+    /// `hash` rather than panicking, since callers may pass one straight
+    /// out of an unverified peer payload (see `validators::attest`).
+    fn location_for(&self, hash: &str) -> anyhow::Result<String>;
+}
+
+/// Artifact hashes are hex SHA-256, so this also doubles as a length check;
+/// shared by every `ArtifactStore` impl that needs to slice `hash` to
+/// shard or address an artifact, so a malformed hash is rejected before
+/// any slicing rather than panicking.
+fn validate_hash(hash: &str) -> anyhow::Result<()> {
+    if hash.len() < 4 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("invalid artifact hash");
+    }
+    Ok(())
+}
+
+/// Stores artifacts on the local filesystem, sharded as `ab/cd/<hash>` so a
+/// single directory never has to hold one entry per artifact ever uploaded.
+pub struct FsArtifactStore {
+    root: PathBuf,
+}
+
+impl FsArtifactStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        FsArtifactStore { root: root.into() }
+    }
+
+    fn path_for(&self, hash: &str) -> anyhow::Result<PathBuf> {
+        validate_hash(hash)?;
+        Ok(self.root.join(&hash[0..2]).join(&hash[2..4]).join(hash))
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for FsArtifactStore {
+    async fn put(&self, hash: &str, bytes: &[u8]) -> anyhow::Result<()> {
+        let path = self.path_for(hash)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        // Content-addressed, so a concurrent writer would produce identical
+        // bytes; a plain write is fine without extra locking.
+        fs::write(path, bytes).await?;
+        Ok(())
+    }
+
+    async fn get(&self, hash: &str) -> anyhow::Result<Box<dyn AsyncRead + Send + Unpin>> {
+        let path = self.path_for(hash)?;
+        let file = fs::File::open(path).await?;
+        Ok(Box::new(file))
+    }
+
+    async fn exists(&self, hash: &str) -> bool {
+        match self.path_for(hash) {
+            Ok(path) => fs::metadata(path).await.is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    fn location_for(&self, hash: &str) -> anyhow::Result<String> {
+        validate_hash(hash)?;
+        Ok(format!("fs:{}/{}/{}", &hash[0..2], &hash[2..4], hash))
+    }
+}
+
+/// `GET /artifact/:hash` — streams the artifact back without buffering the
+/// whole file in memory.
+pub async fn get_artifact(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<crate::AppState>>,
+    axum::extract::Path(hash): axum::extract::Path<String>,
+) -> axum::response::Response {
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    match state.artifact_store.get(&hash).await {
+        Ok(reader) => {
+            let stream = ReaderStream::new(reader);
+            Body::from_stream(stream).into_response()
+        }
+        Err(_) => (StatusCode::NOT_FOUND, "artifact not found").into_response(),
+    }
+}
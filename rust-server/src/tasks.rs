@@ -0,0 +1,278 @@
+use axum::{extract::State, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::rate_limit::RateLimitConfig;
+use crate::ApiResponse;
+
+/// How long a claimed lease is valid for before the sweeper reclaims it.
+const LEASE_SECS: i64 = 600;
+
+/// `get_task` takes a bare, unauthenticated `miner_id`, so without this cap
+/// a single caller could repeat `get_task` with the same id and claim every
+/// `pending` row, starving the queue for `LEASE_SECS` at a time without
+/// ever submitting. Capping concurrent leases per miner bounds how much of
+/// the queue any one id can tie up at once.
+const MAX_CONCURRENT_LEASES_PER_MINER: i64 = 1;
+
+#[derive(FromRow)]
+struct TaskRow {
+    id: String,
+    performance_threshold: f32,
+    validation_data_hash: String,
+    validation_set_size: i64,
+    spot_check_k: i64,
+    performance_tolerance: f32,
+    rate_limit_window_secs: i64,
+    rate_limit_max_requests: i64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TaskInfo {
+    pub task_id: String,
+    pub performance_threshold: f32,
+    pub validation_data_hash: String,
+    pub validation_set_size: u64,
+    pub spot_check_k: u64,
+    pub performance_tolerance: f32,
+    pub rate_limit: RateLimitConfig,
+}
+
+impl From<TaskRow> for TaskInfo {
+    fn from(row: TaskRow) -> Self {
+        TaskInfo {
+            task_id: row.id,
+            performance_threshold: row.performance_threshold,
+            validation_data_hash: row.validation_data_hash,
+            validation_set_size: row.validation_set_size as u64,
+            spot_check_k: row.spot_check_k as u64,
+            performance_tolerance: row.performance_tolerance,
+            rate_limit: RateLimitConfig {
+                window_secs: row.rate_limit_window_secs as u64,
+                max_requests: row.rate_limit_max_requests as u64,
+            },
+        }
+    }
+}
+
+/// Atomically claims one `pending` task for `miner_id`, so concurrent
+/// miners calling `get_task` never race onto the same row. Claims nothing
+/// if `miner_id` is already at `MAX_CONCURRENT_LEASES_PER_MINER` leased or
+/// proposed tasks. Takes a transaction-scoped advisory lock on `miner_id`
+/// first, so two concurrent `get_task` calls for the same miner can't both
+/// read the lease count before either has committed and together blow
+/// past the cap.
+pub async fn claim_task(db: &PgPool, miner_id: i64) -> Result<Option<TaskInfo>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(miner_id)
+        .execute(&mut *tx)
+        .await?;
+    let row = sqlx::query_as::<_, TaskRow>(
+        r#"
+        UPDATE tasks SET status = 'leased', leased_by = $1,
+            lease_expires_at = now() + ($2 * interval '1 second')
+        WHERE id = (
+            SELECT id FROM tasks
+            WHERE status = 'pending'
+            ORDER BY created_at
+            FOR UPDATE SKIP LOCKED
+            LIMIT 1
+        )
+        AND (
+            SELECT count(*) FROM tasks WHERE leased_by = $1 AND status IN ('leased', 'proposed')
+        ) < $3
+        RETURNING id, performance_threshold, validation_data_hash, validation_set_size,
+                  spot_check_k, performance_tolerance, rate_limit_window_secs, rate_limit_max_requests
+        "#,
+    )
+    .bind(miner_id)
+    .bind(LEASE_SECS as f64)
+    .bind(MAX_CONCURRENT_LEASES_PER_MINER)
+    .fetch_optional(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(row.map(Into::into))
+}
+
+/// Extends `miner_id`'s lease on `task_id` if it's still the current holder.
+pub async fn extend_lease(db: &PgPool, task_id: &str, miner_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE tasks SET lease_expires_at = now() + ($1 * interval '1 second')
+        WHERE id = $2 AND leased_by = $3 AND status = 'leased' AND lease_expires_at > now()
+        "#,
+    )
+    .bind(LEASE_SECS as f64)
+    .bind(task_id)
+    .bind(miner_id)
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Returns the task's config only if `miner_id` currently holds an
+/// unexpired lease on it, so `submit` can reject submissions for tasks the
+/// miner was never assigned (or whose lease has since expired).
+pub async fn leased_task(db: &PgPool, task_id: &str, miner_id: i64) -> Result<Option<TaskInfo>, sqlx::Error> {
+    sqlx::query_as::<_, TaskRow>(
+        r#"
+        SELECT id, performance_threshold, validation_data_hash, validation_set_size,
+               spot_check_k, performance_tolerance, rate_limit_window_secs, rate_limit_max_requests
+        FROM tasks
+        WHERE id = $1 AND leased_by = $2 AND status = 'leased' AND lease_expires_at > now()
+        "#,
+    )
+    .bind(task_id)
+    .bind(miner_id)
+    .fetch_optional(db)
+    .await
+    .map(|opt| opt.map(Into::into))
+}
+
+/// Atomically moves `task_id` from `leased` to `proposed` for `miner_id`,
+/// so a second `submit` for the same task_id (same or would-be-reassigned
+/// miner) can no longer also pass the lease check and propose a second,
+/// independently-quorate `pending_ledger` row while this one awaits
+/// finalization. Returns whether the transition actually happened.
+pub async fn mark_proposed(db: &PgPool, task_id: &str, miner_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE tasks SET status = 'proposed'
+        WHERE id = $1 AND leased_by = $2 AND status = 'leased' AND lease_expires_at > now()
+        "#,
+    )
+    .bind(task_id)
+    .bind(miner_id)
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Returns the task's config if `miner_id`'s submission for it is currently
+/// `proposed` (i.e. past `mark_proposed`, awaiting quorum or finalization),
+/// so `validators::attest` can re-fetch the same task a validator needs to
+/// re-run the spot-check against.
+pub async fn proposed_task(db: &PgPool, task_id: &str, miner_id: i64) -> Result<Option<TaskInfo>, sqlx::Error> {
+    sqlx::query_as::<_, TaskRow>(
+        r#"
+        SELECT id, performance_threshold, validation_data_hash, validation_set_size,
+               spot_check_k, performance_tolerance, rate_limit_window_secs, rate_limit_max_requests
+        FROM tasks
+        WHERE id = $1 AND leased_by = $2 AND status = 'proposed'
+        "#,
+    )
+    .bind(task_id)
+    .bind(miner_id)
+    .fetch_optional(db)
+    .await
+    .map(|opt| opt.map(Into::into))
+}
+
+/// Completes `task_id` only if it's still `proposed` for `miner_id`. The
+/// proposal sweeper can revert a task to `pending` (and it can then be
+/// re-leased to a different miner) between when a straggling quorum
+/// starts finalizing and when it gets here, so an unconditional write
+/// would silently stomp whichever miner holds the task by then.
+pub async fn complete_task(db: &PgPool, task_id: &str, miner_id: i64) -> Result<(), sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE tasks SET status = 'completed' WHERE id = $1 AND leased_by = $2 AND status = 'proposed'",
+    )
+    .bind(task_id)
+    .bind(miner_id)
+    .execute(db)
+    .await?;
+    if result.rows_affected() == 0 {
+        warn!(
+            "complete_task: {} is no longer 'proposed' for miner {} (already reassigned); leaving its state as-is",
+            task_id, miner_id
+        );
+    }
+    Ok(())
+}
+
+/// Returns a `proposed` task to `pending` after its `pending_ledger`
+/// proposal expired without reaching quorum, so the lease isn't stuck
+/// forever and the task can be reassigned.
+pub async fn revert_to_pending(db: &PgPool, task_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE tasks SET status = 'pending', leased_by = NULL, lease_expires_at = NULL
+        WHERE id = $1 AND status = 'proposed'
+        "#,
+    )
+    .bind(task_id)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Background sweeper: returns leases that expired without a heartbeat or
+/// a completed submission back to `pending` for reassignment.
+pub fn spawn_lease_sweeper(db: PgPool, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let result = sqlx::query(
+                r#"
+                UPDATE tasks SET status = 'pending', leased_by = NULL, lease_expires_at = NULL
+                WHERE status = 'leased' AND lease_expires_at < now()
+                "#,
+            )
+            .execute(&db)
+            .await;
+            match result {
+                Ok(r) if r.rows_affected() > 0 => {
+                    info!("reclaimed {} expired task lease(s)", r.rows_affected())
+                }
+                Ok(_) => {}
+                Err(e) => error!("lease sweep error: {}", e),
+            }
+        }
+    });
+}
+
+#[derive(Deserialize)]
+pub struct GetTaskRequest {
+    pub miner_id: i64,
+}
+
+/// `POST /get_task` — leases one pending task to the requesting miner.
+pub async fn get_task(
+    State(state): State<Arc<crate::AppState>>,
+    Json(req): Json<GetTaskRequest>,
+) -> impl IntoResponse {
+    match claim_task(&state.db, req.miner_id).await {
+        Ok(Some(task)) => Json(task).into_response(),
+        Ok(None) => Json(ApiResponse {status: "rejected".into(), reason: Some("no_pending_tasks".into())}).into_response(),
+        Err(e) => {
+            error!("claim_task error: {}", e);
+            Json(ApiResponse {status: "rejected".into(), reason: Some("db error".into())}).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct HeartbeatRequest {
+    pub task_id: String,
+    pub miner_id: i64,
+}
+
+/// `POST /heartbeat` — extends the miner's lease on a task it's still working.
+pub async fn heartbeat(
+    State(state): State<Arc<crate::AppState>>,
+    Json(req): Json<HeartbeatRequest>,
+) -> impl IntoResponse {
+    match extend_lease(&state.db, &req.task_id, req.miner_id).await {
+        Ok(true) => Json(ApiResponse {status: "ok".into(), reason: None}),
+        Ok(false) => Json(ApiResponse {status: "rejected".into(), reason: Some("no_active_lease".into())}),
+        Err(e) => {
+            error!("extend_lease error: {}", e);
+            Json(ApiResponse {status: "rejected".into(), reason: Some("db error".into())})
+        }
+    }
+}
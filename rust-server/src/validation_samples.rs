@@ -0,0 +1,26 @@
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+/// Looks up the `(input, label)` preimage committed at each of `indices`
+/// for `task_id`'s validation set. These are the server's own withheld
+/// data, populated when the task's Merkle root was published — never the
+/// miner's — so `submit` can verify a spot-checked prediction against the
+/// real label instead of trusting whatever the miner echoes back.
+pub async fn fetch_samples(
+    db: &PgPool,
+    task_id: &str,
+    indices: &[u64],
+) -> Result<HashMap<u64, (Vec<u8>, Vec<u8>)>, sqlx::Error> {
+    let idx: Vec<i64> = indices.iter().map(|&i| i as i64).collect();
+    let rows: Vec<(i64, Vec<u8>, Vec<u8>)> = sqlx::query_as(
+        r#"
+        SELECT idx, input, label FROM validation_samples
+        WHERE task_id = $1 AND idx = ANY($2)
+        "#,
+    )
+    .bind(task_id)
+    .bind(&idx)
+    .fetch_all(db)
+    .await?;
+    Ok(rows.into_iter().map(|(idx, input, label)| (idx as u64, (input, label))).collect())
+}
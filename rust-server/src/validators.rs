@@ -0,0 +1,521 @@
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use ed25519_dalek::{Keypair, PublicKey, Signature, Signer, Verifier};
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgPool};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::{ApiResponse, SubmissionPayload};
+
+/// How long a `pending_ledger` proposal waits for quorum before the
+/// sweeper expires it.
+const PROPOSAL_TTL_SECS: i64 = 120;
+
+/// This node's validator key, used to sign attestations when a peer
+/// calls our `/attest` endpoint.
+#[derive(Clone)]
+pub struct SelfIdentity {
+    pub validator_id: i64,
+    pub signing_key: Arc<Keypair>,
+}
+
+/// Consensus configuration: who to broadcast proposals to, how many
+/// distinct signatures are required before a proposal is finalized, and
+/// (optionally) this node's own validator identity for when it's acting
+/// as a peer rather than the proposer. An empty peer list or zero quorum
+/// disables the consensus layer entirely, so a single server instance
+/// keeps working exactly as before.
+#[derive(Clone)]
+pub struct ValidatorConfig {
+    pub peers: Vec<String>,
+    pub quorum: u32,
+    pub self_identity: Option<SelfIdentity>,
+}
+
+impl ValidatorConfig {
+    pub fn from_env() -> Self {
+        let peers = std::env::var("VALIDATOR_PEERS")
+            .ok()
+            .map(|v| {
+                v.split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let quorum = std::env::var("VALIDATOR_QUORUM")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        let self_identity = match (
+            std::env::var("VALIDATOR_ID").ok(),
+            std::env::var("VALIDATOR_SIGNING_KEY").ok(),
+        ) {
+            (Some(id), Some(key_hex)) => {
+                let bytes = hex::decode(key_hex).expect("VALIDATOR_SIGNING_KEY must be hex");
+                let key = Keypair::from_bytes(&bytes)
+                    .expect("VALIDATOR_SIGNING_KEY must be a valid ed25519 keypair");
+                Some(SelfIdentity {
+                    validator_id: id.parse().expect("VALIDATOR_ID must be an integer"),
+                    signing_key: Arc::new(key),
+                })
+            }
+            _ => None,
+        };
+        ValidatorConfig { peers, quorum, self_identity }
+    }
+
+    /// Single-node mode: every passing submission is finalized immediately,
+    /// with no peer broadcast. This is what tests use.
+    pub fn disabled() -> Self {
+        ValidatorConfig { peers: Vec::new(), quorum: 0, self_identity: None }
+    }
+
+    pub fn consensus_enabled(&self) -> bool {
+        self.quorum > 0 && !self.peers.is_empty()
+    }
+}
+
+/// The fields a proposal carries from the submission that produced it
+/// through to the real `ledger` row, should it reach quorum.
+pub struct ProposedLedgerRow {
+    pub ledger_id: String,
+    pub task_id: String,
+    pub miner_id: i64,
+    pub performance: f32,
+    pub hyperparameters: serde_json::Value,
+    pub artifact_hash: String,
+    pub artifact_location: String,
+    pub timestamp: u64,
+}
+
+/// `H(ledger_id || task_id || miner_id || performance || artifact_hash ||
+/// artifact_location || hyperparameters || timestamp)` — the canonical
+/// value validators sign attestations over. Takes the same `row` shape
+/// `finalize` copies into the real `ledger`, so a validator's signature
+/// binds everything that ends up in the immutable row, not just a subset.
+pub fn submission_digest(row: &ProposedLedgerRow) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(row.ledger_id.as_bytes());
+    hasher.update(row.task_id.as_bytes());
+    hasher.update(row.miner_id.to_le_bytes());
+    hasher.update(row.performance.to_le_bytes());
+    hasher.update(row.artifact_hash.as_bytes());
+    hasher.update(row.artifact_location.as_bytes());
+    hasher.update(row.hyperparameters.to_string().as_bytes());
+    hasher.update(row.timestamp.to_le_bytes());
+    hasher.finalize().into()
+}
+
+/// Lands a passing submission in `pending_ledger` with status `proposed`,
+/// awaiting either quorum or expiry.
+pub async fn propose(
+    db: &PgPool,
+    row: &ProposedLedgerRow,
+    digest: &[u8; 32],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO pending_ledger(id, task_id, miner_id, performance, hyperparameters,
+            artifact_hash, artifact_location, timestamp, digest, status, expires_at)
+        VALUES ($1,$2,$3,$4,$5,$6,$7, to_timestamp($8), $9, 'proposed',
+            now() + ($10 * interval '1 second'))
+        "#,
+    )
+    .bind(&row.ledger_id)
+    .bind(&row.task_id)
+    .bind(row.miner_id)
+    .bind(row.performance as f64)
+    .bind(&row.hyperparameters)
+    .bind(&row.artifact_hash)
+    .bind(&row.artifact_location)
+    .bind(row.timestamp as i64)
+    .bind(hex::encode(digest))
+    .bind(PROPOSAL_TTL_SECS as f64)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Promotes a proposal to the real `ledger`, marks it `finalized`, and
+/// releases the task it was submitted against. Guarded on the proposal
+/// still being `proposed`: [`spawn_proposal_sweeper`] can expire the same
+/// proposal concurrently if a straggling quorum completes right after its
+/// `expires_at`, and without this guard the now-expired proposal would
+/// still land in the permanent ledger. No-ops (logging) if it's already
+/// moved on.
+pub async fn finalize(db: &PgPool, ledger_id: &str, task_id: &str) -> Result<(), sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let inserted: Option<(i64,)> = sqlx::query_as(
+        r#"
+        INSERT INTO ledger(id, task_id, miner_id, performance, hyperparameters,
+            artifact_hash, artifact_location, timestamp)
+        SELECT id, task_id, miner_id, performance, hyperparameters,
+               artifact_hash, artifact_location, timestamp
+        FROM pending_ledger WHERE id = $1 AND status = 'proposed'
+        RETURNING miner_id
+        "#,
+    )
+    .bind(ledger_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let miner_id = match inserted {
+        Some((miner_id,)) => miner_id,
+        None => {
+            tx.rollback().await?;
+            warn!(
+                "finalize called for {} but it is no longer 'proposed' (already finalized or expired); skipping",
+                ledger_id
+            );
+            return Ok(());
+        }
+    };
+    sqlx::query("UPDATE pending_ledger SET status = 'finalized' WHERE id = $1")
+        .bind(ledger_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    if let Err(e) = crate::tasks::complete_task(db, task_id, miner_id).await {
+        error!("complete_task error after finalizing {}: {}", ledger_id, e);
+    }
+    Ok(())
+}
+
+/// Verifies `signature_hex` against `validator_id`'s stored public key and
+/// the proposal's digest, then records it (duplicates are ignored via the
+/// unique `(ledger_id, validator_id)` constraint on `attestations`).
+/// Returns whether this call actually added a new, valid attestation.
+pub async fn record_attestation(
+    db: &PgPool,
+    ledger_id: &str,
+    validator_id: i64,
+    signature_hex: &str,
+) -> anyhow::Result<bool> {
+    let pubkey_hex: String =
+        sqlx::query_scalar("SELECT public_key FROM validators WHERE validator_id = $1")
+            .bind(validator_id)
+            .fetch_one(db)
+            .await?;
+    let digest_hex: String =
+        sqlx::query_scalar("SELECT digest FROM pending_ledger WHERE id = $1")
+            .bind(ledger_id)
+            .fetch_one(db)
+            .await?;
+
+    let vk = PublicKey::from_bytes(&hex::decode(pubkey_hex)?)?;
+    let sig = Signature::from_bytes(&hex::decode(signature_hex)?)?;
+    let digest = hex::decode(&digest_hex)?;
+    if vk.verify(&digest, &sig).is_err() {
+        warn!(
+            "validator {} returned an invalid attestation for {}",
+            validator_id, ledger_id
+        );
+        return Ok(false);
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO attestations(ledger_id, validator_id, signature) VALUES ($1,$2,$3) ON CONFLICT DO NOTHING",
+    )
+    .bind(ledger_id)
+    .bind(validator_id)
+    .bind(signature_hex)
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn attestation_count(db: &PgPool, ledger_id: &str) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT count(*) FROM attestations WHERE ledger_id = $1")
+        .bind(ledger_id)
+        .fetch_one(db)
+        .await
+}
+
+#[derive(Serialize)]
+struct AttestRequest<'a> {
+    ledger_id: &'a str,
+    digest_hex: &'a str,
+    payload_json: &'a str,
+    signature_hex: &'a str,
+}
+
+/// Body of an inbound `POST /attest` call (owned, unlike the outbound
+/// [`AttestRequest`] we send as the proposer).
+#[derive(Deserialize)]
+pub struct AttestIn {
+    pub ledger_id: String,
+    pub digest_hex: String,
+    pub payload_json: String,
+    pub signature_hex: String,
+}
+
+/// Response to `POST /attest`: `status` is `"attested"` with the
+/// validator's id and signature filled in, or `"rejected"` with `reason`
+/// set when this node's own re-verification didn't pass.
+#[derive(Serialize, Deserialize)]
+struct AttestOut {
+    status: String,
+    reason: Option<String>,
+    validator_id: Option<i64>,
+    signature_hex: Option<String>,
+}
+
+impl AttestOut {
+    fn rejected(reason: &str) -> Self {
+        AttestOut { status: "rejected".into(), reason: Some(reason.into()), validator_id: None, signature_hex: None }
+    }
+}
+
+/// Broadcasts the proposal to every configured peer concurrently, records
+/// whatever valid attestations come back, and finalizes the row as soon
+/// as quorum is reached without waiting on stragglers. Runs in the
+/// background (spawned from `submit`) so a slow or unreachable peer never
+/// blocks the miner's response; a proposal that never reaches quorum is
+/// cleaned up later by [`spawn_proposal_sweeper`]. Broadcasting
+/// concurrently (rather than one peer at a time) bounds this round's
+/// latency by the slowest single peer instead of the sum of all of
+/// them, so a round with enough peers can't itself blow past
+/// `PROPOSAL_TTL_SECS` before ever reaching the later ones.
+pub fn spawn_attestation_round(
+    db: PgPool,
+    config: ValidatorConfig,
+    ledger_id: String,
+    task_id: String,
+    digest: [u8; 32],
+    payload_json: String,
+    signature_hex: String,
+) {
+    tokio::spawn(async move {
+        let client = reqwest::Client::new();
+        let digest_hex = hex::encode(digest);
+        let mut gathered = 0u32;
+
+        let mut calls: FuturesUnordered<_> = config
+            .peers
+            .iter()
+            .map(|peer| {
+                let client = client.clone();
+                let ledger_id = &ledger_id;
+                let digest_hex = &digest_hex;
+                let payload_json = &payload_json;
+                let signature_hex = &signature_hex;
+                async move {
+                    let resp = client
+                        .post(format!("{}/attest", peer))
+                        .json(&AttestRequest {
+                            ledger_id,
+                            digest_hex,
+                            payload_json,
+                            signature_hex,
+                        })
+                        .timeout(Duration::from_secs(10))
+                        .send()
+                        .await;
+                    (peer, resp)
+                }
+            })
+            .collect();
+
+        while let Some((peer, resp)) = calls.next().await {
+            let attestation: AttestOut = match resp {
+                Ok(r) => match r.json().await {
+                    Ok(a) => a,
+                    Err(e) => {
+                        warn!("peer {} returned an unparsable attestation: {}", peer, e);
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    warn!("peer {} unreachable for attestation: {}", peer, e);
+                    continue;
+                }
+            };
+            let (validator_id, signature_hex) = match (attestation.validator_id, attestation.signature_hex) {
+                (Some(id), Some(sig)) if attestation.status == "attested" => (id, sig),
+                _ => {
+                    warn!(
+                        "peer {} declined to attest: {}",
+                        peer,
+                        attestation.reason.as_deref().unwrap_or("unknown")
+                    );
+                    continue;
+                }
+            };
+            match record_attestation(&db, &ledger_id, validator_id, &signature_hex).await {
+                Ok(true) => {
+                    gathered += 1;
+                    if gathered >= config.quorum {
+                        break;
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => error!("recording attestation from validator {} failed: {}", validator_id, e),
+            }
+        }
+
+        match attestation_count(&db, &ledger_id).await {
+            Ok(n) if n >= config.quorum as i64 => {
+                if let Err(e) = finalize(&db, &ledger_id, &task_id).await {
+                    error!("finalize error for {}: {}", ledger_id, e);
+                }
+            }
+            Ok(n) => info!(
+                "{} gathered {}/{} attestations; awaiting more or expiry",
+                ledger_id, n, config.quorum
+            ),
+            Err(e) => error!("attestation_count error: {}", e),
+        }
+    });
+}
+
+/// Background sweeper: proposals that never reach quorum before their
+/// `expires_at` are marked `expired` rather than lingering as `proposed`
+/// forever.
+pub fn spawn_proposal_sweeper(db: PgPool, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let result: Result<Vec<(String,)>, sqlx::Error> = sqlx::query_as(
+                "UPDATE pending_ledger SET status = 'expired' WHERE status = 'proposed' AND expires_at < now() RETURNING task_id",
+            )
+            .fetch_all(&db)
+            .await;
+            match result {
+                Ok(rows) if !rows.is_empty() => {
+                    info!("expired {} pending ledger proposal(s) lacking quorum", rows.len());
+                    for (task_id,) in rows {
+                        if let Err(e) = crate::tasks::revert_to_pending(&db, &task_id).await {
+                            error!("failed to revert task {} to pending after expiry: {}", task_id, e);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => error!("pending ledger sweep error: {}", e),
+            }
+        }
+    });
+}
+
+/// `POST /attest` — called by the proposing peer. Independently re-derives
+/// the digest, re-verifies the miner's signature, and re-runs `submit`'s
+/// lease, timestamp-freshness, Merkle spot-check and performance-tolerance
+/// checks before signing, so quorum actually
+/// requires independent agreement rather than trusting the proposer's
+/// say-so. The artifact bytes never reach a validator, so the artifact
+/// hash is not re-checked against them, and the rate-limit and replay
+/// checks are not repeated. A node with no validator identity configured
+/// can't serve this endpoint.
+pub async fn attest(
+    State(state): State<Arc<crate::AppState>>,
+    Json(req): Json<AttestIn>,
+) -> impl IntoResponse {
+    let identity = match &state.validator_config.self_identity {
+        Some(id) => id,
+        None => return Json(AttestOut::rejected("not_a_validator")),
+    };
+
+    let payload: SubmissionPayload = match serde_json::from_str(&req.payload_json) {
+        Ok(p) => p,
+        Err(_) => return Json(AttestOut::rejected("invalid payload json")),
+    };
+
+    // Independently re-derived, same as `submit`, rather than trusted from
+    // the proposer: a validator configured against the same artifact
+    // backend lands on the same location for a given hash. `payload` came
+    // straight out of `req.payload_json`, an unverified peer field, so a
+    // malformed hash must be rejected here rather than panicking.
+    let artifact_location = match state.artifact_store.location_for(&payload.artifact_hash) {
+        Ok(loc) => loc,
+        Err(_) => return Json(AttestOut::rejected("invalid_artifact_hash")),
+    };
+    let expected_digest = submission_digest(&ProposedLedgerRow {
+        ledger_id: req.ledger_id.clone(),
+        task_id: payload.task_id.clone(),
+        miner_id: payload.miner_id,
+        performance: payload.performance,
+        hyperparameters: payload.hyperparameters.clone(),
+        artifact_hash: payload.artifact_hash.clone(),
+        artifact_location,
+        timestamp: payload.timestamp,
+    });
+    if hex::encode(expected_digest) != req.digest_hex {
+        return Json(AttestOut::rejected("digest_mismatch"));
+    }
+
+    let pubkey_hex: String = match sqlx::query_scalar("SELECT public_key FROM miners WHERE miner_id = $1")
+        .bind(payload.miner_id)
+        .fetch_one(&state.db)
+        .await
+    {
+        Ok(pk) => pk,
+        Err(_) => return Json(AttestOut::rejected("unknown miner")),
+    };
+    let verified = hex::decode(&pubkey_hex)
+        .ok()
+        .and_then(|b| PublicKey::from_bytes(&b).ok())
+        .zip(hex::decode(&req.signature_hex).ok().and_then(|b| Signature::from_bytes(&b).ok()))
+        .map(|(vk, sig)| vk.verify(req.payload_json.as_bytes(), &sig).is_ok())
+        .unwrap_or(false);
+    if !verified {
+        return Json(AttestOut::rejected("bad_signature"));
+    }
+
+    let task = match crate::tasks::proposed_task(&state.db, &payload.task_id, payload.miner_id).await {
+        Ok(Some(t)) => t,
+        Ok(None) => return Json(AttestOut::rejected("no_active_lease")),
+        Err(e) => {
+            error!("proposed_task lookup error during attest: {}", e);
+            return Json(AttestOut::rejected("db error"));
+        }
+    };
+    if let Err(reason) = crate::check_timestamp_freshness(payload.timestamp) {
+        return Json(AttestOut::rejected(reason));
+    }
+    if let Err(reason) = crate::verify_spot_check(&state.db, &task, &payload).await {
+        return Json(AttestOut::rejected(reason));
+    }
+
+    let signature = identity.signing_key.sign(&expected_digest);
+    Json(AttestOut {
+        status: "attested".into(),
+        reason: None,
+        validator_id: Some(identity.validator_id),
+        signature_hex: Some(hex::encode(signature.to_bytes())),
+    })
+}
+
+#[derive(Serialize, FromRow)]
+pub struct AttestationRow {
+    pub validator_id: i64,
+    pub signature: String,
+}
+
+/// `GET /attestations/:ledger_id` — lists which validators have signed
+/// off on a (possibly still-pending) ledger entry.
+pub async fn attestations(
+    State(state): State<Arc<crate::AppState>>,
+    Path(ledger_id): Path<String>,
+) -> impl IntoResponse {
+    match sqlx::query_as::<_, AttestationRow>(
+        "SELECT validator_id, signature FROM attestations WHERE ledger_id = $1",
+    )
+    .bind(&ledger_id)
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(rows) => Json(rows).into_response(),
+        Err(e) => {
+            error!("attestations lookup error: {}", e);
+            Json(ApiResponse { status: "rejected".into(), reason: Some("db error".into()) }).into_response()
+        }
+    }
+}
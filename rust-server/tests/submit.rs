@@ -0,0 +1,353 @@
+//! End-to-end tests for `POST /submit`, run against a real Postgres test
+//! database (schema assumed pre-migrated, as in production) but a mock
+//! `NonceStore` so the anti-replay path doesn't need a live Redis.
+//!
+//! `#[ignore]`d by default since they need `TEST_DATABASE_URL` or
+//! `DATABASE_URL` pointed at that database; run them with
+//! `cargo test -- --ignored`.
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use ed25519_dalek::{Keypair, Signer};
+use rand::rngs::OsRng;
+use rust_server::{
+    build_router, ApiResponse, ArtifactStore, AppState, FsArtifactStore, LocalRateLimiter,
+    MockNonceStore, ValidatorConfig,
+};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+const BOUNDARY: &str = "X-TEST-BOUNDARY";
+/// Merkle root over 4 leaves `H(index || "" || "")`, index 0..4.
+const VALIDATION_ROOT: &str = "da82b2152f6b1ca855d1920d2e2786e08dd7b95ac04de92d7da3d852485bc636";
+
+/// Matches `VALIDATION_ROOT`'s tree; every submission must spot-check all 4.
+/// Inputs/labels are *not* part of the payload — the server looks those up
+/// in `validation_samples` itself (seeded by `test_state`).
+fn valid_spot_check() -> serde_json::Value {
+    serde_json::json!([
+        {"index": 0, "prediction_hex": "",
+         "proof_hex": ["7c9fa136d4413fa6173637e883b6998d32e1d675f88cddff9dcbcf331820f4b8",
+                       "c9b3e35b15778715742a4899bbda46ab23793cd9109232fbebbacdbb86353f41"]},
+        {"index": 1, "prediction_hex": "",
+         "proof_hex": ["af5570f5a1810b7af78caf4bc70a660f0df51e42baf91d4de5b2328de0e83dfc",
+                       "c9b3e35b15778715742a4899bbda46ab23793cd9109232fbebbacdbb86353f41"]},
+        {"index": 2, "prediction_hex": "",
+         "proof_hex": ["35be322d094f9d154a8aba4733b8497f180353bd7ae7b0a15f90b586b549f28b",
+                       "c82191a310bcd974e428362f9ca9efddd2155f2ca4cc7451c366585c690209ef"]},
+        {"index": 3, "prediction_hex": "",
+         "proof_hex": ["d86e8112f3c4c4442126f8e9f44f16867da487f29052bf91b810457db34209a4",
+                       "c82191a310bcd974e428362f9ca9efddd2155f2ca4cc7451c366585c690209ef"]},
+    ])
+}
+
+/// Builds app state plus a `tasks` row already leased to a fresh miner, as
+/// if the miner had just called `get_task`. The task id is unique per call
+/// so parallel tests never contend over the same lease.
+async fn test_state() -> (Arc<AppState>, Keypair, i64, String) {
+    let database_url = std::env::var("TEST_DATABASE_URL")
+        .or_else(|_| std::env::var("DATABASE_URL"))
+        .expect("TEST_DATABASE_URL or DATABASE_URL required to run submit integration tests");
+    let db = sqlx::PgPool::connect(&database_url).await.unwrap();
+
+    let mut csprng = OsRng {};
+    let keypair = Keypair::generate(&mut csprng);
+    let miner_id: i64 = rand::random::<u32>() as i64;
+    sqlx::query("INSERT INTO miners(miner_id, public_key) VALUES ($1, $2)")
+        .bind(miner_id)
+        .bind(hex::encode(keypair.public.to_bytes()))
+        .execute(&db)
+        .await
+        .unwrap();
+
+    let task_id = format!("task-test-{}", miner_id);
+    sqlx::query(
+        r#"
+        INSERT INTO tasks (id, performance_threshold, validation_data_hash, validation_set_size,
+            spot_check_k, performance_tolerance, rate_limit_window_secs, rate_limit_max_requests,
+            status, leased_by, lease_expires_at)
+        VALUES ($1, 0.90, $2, 4, 4, 0.05, 60, 30, 'leased', $3, now() + interval '600 seconds')
+        "#,
+    )
+    .bind(&task_id)
+    .bind(VALIDATION_ROOT)
+    .bind(miner_id)
+    .execute(&db)
+    .await
+    .unwrap();
+
+    for idx in 0..4i64 {
+        sqlx::query(
+            "INSERT INTO validation_samples (task_id, idx, input, label) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(&task_id)
+        .bind(idx)
+        .bind(Vec::<u8>::new())
+        .bind(Vec::<u8>::new())
+        .execute(&db)
+        .await
+        .unwrap();
+    }
+
+    let tmp = std::env::temp_dir().join(format!("artifacts-test-{}", miner_id));
+    let artifact_store: Arc<dyn ArtifactStore> = Arc::new(FsArtifactStore::new(tmp));
+    let (ledger_events, _) = tokio::sync::broadcast::channel(16);
+    // Flush threshold high enough that these tests never round-trip to a real Redis.
+    let local_limiter = Arc::new(LocalRateLimiter::new(
+        "redis://127.0.0.1/",
+        1_000_000,
+        Duration::from_secs(3600),
+    ));
+
+    let state = Arc::new(AppState {
+        db,
+        local_limiter,
+        ledger_events,
+        artifact_store,
+        nonce_store: Arc::new(MockNonceStore::new()),
+        validator_config: ValidatorConfig::disabled(),
+    });
+    (state, keypair, miner_id, task_id)
+}
+
+fn multipart_body(payload_json: &str, signature_hex: &str, artifact: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    let mut part = |name: &str, value: &[u8]| {
+        body.extend_from_slice(format!("--{}\r\n", BOUNDARY).as_bytes());
+        body.extend_from_slice(
+            format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name).as_bytes(),
+        );
+        body.extend_from_slice(value);
+        body.extend_from_slice(b"\r\n");
+    };
+    part("payload", payload_json.as_bytes());
+    part("signature", signature_hex.as_bytes());
+    part("artifact", artifact);
+    body.extend_from_slice(format!("--{}--\r\n", BOUNDARY).as_bytes());
+    body
+}
+
+fn sign_submission(
+    keypair: &Keypair,
+    task_id: &str,
+    miner_id: i64,
+    artifact: &[u8],
+    nonce: u64,
+) -> (String, String) {
+    let artifact_hash = hex::encode(Sha256::digest(artifact));
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let payload_json = serde_json::json!({
+        "task_id": task_id,
+        "miner_id": miner_id,
+        "performance": 0.95,
+        "artifact_hash": artifact_hash,
+        "hyperparameters": {},
+        "timestamp": now,
+        "nonce": nonce,
+        "spot_check": valid_spot_check(),
+    })
+    .to_string();
+    let signature = keypair.sign(payload_json.as_bytes());
+    (payload_json, hex::encode(signature.to_bytes()))
+}
+
+async fn send(state: Arc<AppState>, body: Vec<u8>) -> ApiResponse {
+    let app = build_router(state);
+    let req = Request::builder()
+        .method("POST")
+        .uri("/submit")
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={}", BOUNDARY),
+        )
+        .body(Body::from(body))
+        .unwrap();
+    let resp = app.oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires a live Postgres: set TEST_DATABASE_URL or DATABASE_URL and run with `cargo test -- --ignored`"]
+async fn accepts_well_formed_submission() {
+    let (state, keypair, miner_id, task_id) = test_state().await;
+    let artifact = b"model-bytes".to_vec();
+    let (payload_json, signature_hex) = sign_submission(&keypair, &task_id, miner_id, &artifact, 1);
+    let body = multipart_body(&payload_json, &signature_hex, &artifact);
+
+    let resp = send(state, body).await;
+    assert_eq!(resp.status, "accepted");
+}
+
+#[tokio::test]
+#[ignore = "requires a live Postgres: set TEST_DATABASE_URL or DATABASE_URL and run with `cargo test -- --ignored`"]
+async fn rejects_replayed_submission() {
+    let (state, keypair, miner_id, task_id) = test_state().await;
+    let artifact = b"model-bytes".to_vec();
+    let (payload_json, signature_hex) = sign_submission(&keypair, &task_id, miner_id, &artifact, 1);
+    let body = multipart_body(&payload_json, &signature_hex, &artifact);
+
+    let first = send(state.clone(), body.clone()).await;
+    assert_eq!(first.status, "accepted");
+
+    // Dynamic leasing consumes the task's lease once a submission passes
+    // every check, so a byte-for-byte replay now hits the lease
+    // check before the nonce check is ever consulted -- still a hard
+    // rejection, just via a different gate than the nonce store itself.
+    let second = send(state, body).await;
+    assert_eq!(second.status, "rejected");
+    assert_eq!(second.reason.as_deref(), Some("no_active_lease"));
+}
+
+#[tokio::test]
+#[ignore = "requires a live Postgres: set TEST_DATABASE_URL or DATABASE_URL and run with `cargo test -- --ignored`"]
+async fn rejects_stale_timestamp() {
+    let (state, keypair, miner_id, task_id) = test_state().await;
+    let artifact = b"model-bytes".to_vec();
+    let artifact_hash = hex::encode(Sha256::digest(&artifact));
+    let stale_payload = serde_json::json!({
+        "task_id": task_id,
+        "miner_id": miner_id,
+        "performance": 0.95,
+        "artifact_hash": artifact_hash,
+        "hyperparameters": {},
+        "timestamp": 0,
+        "nonce": 2,
+        "spot_check": valid_spot_check(),
+    })
+    .to_string();
+    let signature = keypair.sign(stale_payload.as_bytes());
+    let body = multipart_body(&stale_payload, &hex::encode(signature.to_bytes()), &artifact);
+
+    let resp = send(state, body).await;
+    assert_eq!(resp.status, "rejected");
+    assert_eq!(resp.reason.as_deref(), Some("stale timestamp"));
+}
+
+#[tokio::test]
+#[ignore = "requires a live Postgres: set TEST_DATABASE_URL or DATABASE_URL and run with `cargo test -- --ignored`"]
+async fn accepts_near_future_timestamp() {
+    // Within the 60s clock-skew allowance, `now` is still less than
+    // `payload.timestamp`, so `check_timestamp_freshness` must use a
+    // saturating subtraction here rather than panicking on underflow.
+    let (state, keypair, miner_id, task_id) = test_state().await;
+    let artifact = b"model-bytes".to_vec();
+    let artifact_hash = hex::encode(Sha256::digest(&artifact));
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let payload_json = serde_json::json!({
+        "task_id": task_id,
+        "miner_id": miner_id,
+        "performance": 0.95,
+        "artifact_hash": artifact_hash,
+        "hyperparameters": {},
+        "timestamp": now + 30,
+        "nonce": 8,
+        "spot_check": valid_spot_check(),
+    })
+    .to_string();
+    let signature = keypair.sign(payload_json.as_bytes());
+    let body = multipart_body(&payload_json, &hex::encode(signature.to_bytes()), &artifact);
+
+    let resp = send(state, body).await;
+    assert_eq!(resp.status, "accepted");
+}
+
+#[tokio::test]
+#[ignore = "requires a live Postgres: set TEST_DATABASE_URL or DATABASE_URL and run with `cargo test -- --ignored`"]
+async fn rejects_artifact_hash_mismatch() {
+    let (state, keypair, miner_id, task_id) = test_state().await;
+    let artifact = b"model-bytes".to_vec();
+    let (payload_json, signature_hex) = sign_submission(&keypair, &task_id, miner_id, &artifact, 3);
+    let wrong_artifact = b"different-bytes".to_vec();
+    let body = multipart_body(&payload_json, &signature_hex, &wrong_artifact);
+
+    let resp = send(state, body).await;
+    assert_eq!(resp.status, "rejected");
+    assert_eq!(resp.reason.as_deref(), Some("artifact hash mismatch"));
+}
+
+#[tokio::test]
+#[ignore = "requires a live Postgres: set TEST_DATABASE_URL or DATABASE_URL and run with `cargo test -- --ignored`"]
+async fn rejects_invalid_merkle_proof() {
+    let (state, keypair, miner_id, task_id) = test_state().await;
+    let artifact = b"model-bytes".to_vec();
+    let artifact_hash = hex::encode(Sha256::digest(&artifact));
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut spot_check = valid_spot_check();
+    spot_check[0]["proof_hex"][0] = serde_json::json!("00".repeat(32));
+    let payload_json = serde_json::json!({
+        "task_id": task_id,
+        "miner_id": miner_id,
+        "performance": 0.95,
+        "artifact_hash": artifact_hash,
+        "hyperparameters": {},
+        "timestamp": now,
+        "nonce": 6,
+        "spot_check": spot_check,
+    })
+    .to_string();
+    let signature = keypair.sign(payload_json.as_bytes());
+    let body = multipart_body(&payload_json, &hex::encode(signature.to_bytes()), &artifact);
+
+    let resp = send(state, body).await;
+    assert_eq!(resp.status, "rejected");
+    assert_eq!(resp.reason.as_deref(), Some("invalid_merkle_proof"));
+}
+
+#[tokio::test]
+#[ignore = "requires a live Postgres: set TEST_DATABASE_URL or DATABASE_URL and run with `cargo test -- --ignored`"]
+async fn rejects_performance_overclaim() {
+    let (state, keypair, miner_id, task_id) = test_state().await;
+    let artifact = b"model-bytes".to_vec();
+    let artifact_hash = hex::encode(Sha256::digest(&artifact));
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut spot_check = valid_spot_check();
+    for entry in spot_check.as_array_mut().unwrap() {
+        entry["prediction_hex"] = serde_json::json!(hex::encode(b"wrong"));
+    }
+    let payload_json = serde_json::json!({
+        "task_id": task_id,
+        "miner_id": miner_id,
+        "performance": 0.95,
+        "artifact_hash": artifact_hash,
+        "hyperparameters": {},
+        "timestamp": now,
+        "nonce": 7,
+        "spot_check": spot_check,
+    })
+    .to_string();
+    let signature = keypair.sign(payload_json.as_bytes());
+    let body = multipart_body(&payload_json, &hex::encode(signature.to_bytes()), &artifact);
+
+    let resp = send(state, body).await;
+    assert_eq!(resp.status, "rejected");
+    assert_eq!(resp.reason.as_deref(), Some("performance_overclaim"));
+}
+
+#[tokio::test]
+#[ignore = "requires a live Postgres: set TEST_DATABASE_URL or DATABASE_URL and run with `cargo test -- --ignored`"]
+async fn rejects_bad_signature() {
+    let (state, keypair, miner_id, task_id) = test_state().await;
+    let artifact = b"model-bytes".to_vec();
+    let (payload_json, _good_sig) = sign_submission(&keypair, &task_id, miner_id, &artifact, 4);
+    let (_other_payload, other_sig) = sign_submission(&keypair, &task_id, miner_id, b"other", 5);
+    let body = multipart_body(&payload_json, &other_sig, &artifact);
+
+    let resp = send(state, body).await;
+    assert_eq!(resp.status, "rejected");
+    assert_eq!(resp.reason.as_deref(), Some("bad_signature"));
+}